@@ -7,6 +7,18 @@ use std::vec::Vec;
 /// candidate, and a "**" segment in the pattern will match any set of segments.
 /// **Using an empty seperator will result in no string splitting**
 /// in the candidate.
+///
+/// Beyond "*" and "**", a pattern may also use:
+/// - `?` to match exactly one character within a segment
+/// - `[...]` to match one character from a class, e.g. `[a-z0-9]`, or `[!...]`
+///   to match one character *not* in the class. Class members are single
+///   bytes, so only ASCII characters can be listed in a class: a non-ASCII
+///   candidate character never matches `[...]` and always matches `[!...]`
+/// - `{a,b,...}` to alternate between literal options, which may themselves
+///   span separators, e.g. `user-[0-9]*/{read,write}`
+///
+/// Any of `\*`, `\?`, `\[`, `\{`, `\,`, `\}` or `\\` escapes that character so
+/// it is matched literally instead of being interpreted as glob syntax.
 /// # Examples
 /// ## Simple matching
 /// ```
@@ -31,7 +43,21 @@ use std::vec::Vec;
 /// assert_eq!(true, matches("a/**/*.jpg", "a/foo/bar/baz.jpg", "/"));
 /// assert_eq!(false, matches("a/**/*.jpg", "a/foo/bar/baz", "/"));
 /// ```
+/// ## Matching with `?`, character classes, and brace alternation
+/// ```
+/// use hapolicy::glob::matches;
+/// assert_eq!(true, matches("user-[0-9]*/{read,write}", "user-42/read", "/"));
+/// assert_eq!(true, matches("user-[0-9]*/{read,write}", "user-42/write", "/"));
+/// assert_eq!(false, matches("user-[0-9]*/{read,write}", "user-42/delete", "/"));
+/// assert_eq!(true, matches("a?c", "abc", ""));
+/// assert_eq!(true, matches("[!a-z]", "A", ""));
+/// assert_eq!(true, matches("a\\*b", "a*b", ""));
+/// ```
 pub fn matches(pattern: &str, candidate: &str, sep: &str) -> bool {
+    expand_braces(pattern).iter().any(|alternative| matches_no_braces(alternative, candidate, sep))
+}
+
+fn matches_no_braces(pattern: &str, candidate: &str, sep: &str) -> bool {
     let pattern: Vec<&str> = match sep {
         "" => vec!(pattern),
         _ => pattern.split(sep).collect(),
@@ -43,42 +69,623 @@ pub fn matches(pattern: &str, candidate: &str, sep: &str) -> bool {
     matches_segments(&pattern[..], &candidate[..])
 }
 
-fn matches_segments(pattern: &[&str], candidate: &[&str]) -> bool {
-    if pattern.len() == 0 {
-        // We need to run out of pattern and candidate at the same time
-        candidate.len() == 0
-    } else if candidate.len() == 0 {
-        // If the candidate ends before the pattern, reject it. The candidate
-        // must not be more general than the pattern. Only exception is ending on "**".
-        pattern.len() == 1 && pattern[0] == "**"
-    } else if pattern[0] == "**" {
-        // In this case the path can span multiple directories. For simplicity,
-        // it's not allowed to have a partial match here, so the pattern must be
-        // exactly "**". Here we'll follow a "use it or lose it strategy" where
-        // each recursive time we either consume a candidate segment, or we stop consuming.
-        matches_segments(&pattern[1..], candidate) || matches_segments(pattern, &candidate[1..])
-    } else if pattern[0].contains("*") {
-        // This is the case where we have a single wildcard in in the pattern. We'll defer
-        // to a helper function to help
-        matches_glob(pattern[0], candidate[0]) && matches_segments(&pattern[1..], &candidate[1..])
+// The cross product of nested brace groups is exponential in the number of
+// groups (e.g. "{aa,bb}" repeated n times yields 2^n alternatives), so the
+// total number of concrete patterns `expand_braces` will produce is capped.
+// A pattern that would exceed the cap is treated as having no brace group at
+// all, rather than spending unbounded time expanding it.
+const MAX_BRACE_ALTERNATIVES: usize = 64;
+
+// Expands `{a,b,...}` brace alternation into the cross product of concrete
+// patterns, e.g. "a/{b,c}" becomes ["a/b", "a/c"]. An escaped brace or comma
+// (`\{`, `\}`, `\,`) is left untouched for the tokenizer to interpret as a
+// literal character. A pattern with no (unescaped) brace group expands to
+// itself. Unterminated brace groups are left as literal text.
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let mut budget = MAX_BRACE_ALTERNATIVES;
+    expand_braces_bounded(pattern, &mut budget).unwrap_or_else(|| vec!(pattern.to_string()))
+}
+
+// Same as `expand_braces`, but bails out with `None` as soon as the number of
+// concrete patterns produced would exceed `budget`, which is decremented once
+// per finished (brace-free) alternative across the whole recursion.
+fn expand_braces_bounded(pattern: &str, budget: &mut usize) -> Option<Vec<String>> {
+    match find_brace_group(pattern) {
+        None => {
+            if *budget == 0 {
+                return None;
+            }
+            *budget -= 1;
+            Some(vec!(pattern.to_string()))
+        }
+        Some((prefix, alternatives, suffix)) => {
+            let mut expanded = Vec::new();
+            for alternative in &alternatives {
+                for tail in expand_braces_bounded(&(alternative.clone() + &suffix), budget)? {
+                    expanded.push(format!("{}{}", prefix, tail));
+                }
+            }
+            Some(expanded)
+        }
+    }
+}
+
+// Finds the first unescaped "{...}" group in `pattern` and splits it into the
+// text before the group, the group's comma-separated alternatives, and the
+// text after the group. Brace groups do not nest. A "[...]" character class
+// is skipped wholesale (the same way `parse_class` would parse it), since any
+// "{", ",", or "}" inside one is a literal class member, not brace syntax.
+fn find_brace_group(pattern: &str) -> Option<(String, Vec<String>, String)> {
+    let bytes = pattern.as_bytes();
+    let mut open = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 1 < bytes.len() {
+            i += 2;
+        } else if bytes[i] == b'[' {
+            i += match parse_class(&bytes[i..]) {
+                Some((_, consumed)) => consumed,
+                None => 1,
+            };
+        } else if bytes[i] == b'{' {
+            open = Some(i);
+            break;
+        } else {
+            i += 1;
+        }
+    }
+    let open = open?;
+
+    let mut alternatives = Vec::new();
+    let mut part_start = open + 1;
+    let mut close = None;
+    let mut j = part_start;
+    while j < bytes.len() {
+        if bytes[j] == b'\\' && j + 1 < bytes.len() {
+            j += 2;
+        } else if bytes[j] == b'[' {
+            j += match parse_class(&bytes[j..]) {
+                Some((_, consumed)) => consumed,
+                None => 1,
+            };
+        } else if bytes[j] == b',' {
+            alternatives.push(pattern[part_start..j].to_string());
+            part_start = j + 1;
+            j += 1;
+        } else if bytes[j] == b'}' {
+            alternatives.push(pattern[part_start..j].to_string());
+            close = Some(j);
+            break;
+        } else {
+            j += 1;
+        }
+    }
+    close.map(|close| (pattern[..open].to_string(), alternatives, pattern[close + 1..].to_string()))
+}
+
+/// A set of glob patterns that can be tested against a single candidate in one call.
+/// This is useful for policy evaluation, where a single action or resource needs to
+/// be checked against every pattern across every statement in a document. Every
+/// pattern is compiled once when the set is built; at match time the patterns are
+/// still tried one by one, and the candidate is split once for each pattern tried.
+/// # Examples
+/// ```
+/// use hapolicy::glob::GlobSet;
+/// let set = GlobSet::new(vec![
+///     ("a/*", "/"),
+///     ("b/*", "/"),
+///     ("a/**", "/"),
+/// ]);
+/// assert_eq!(true, set.is_match("a/foo"));
+/// assert_eq!(vec![0, 2], set.matches("a/foo"));
+/// assert_eq!(vec![1], set.matches("b/foo"));
+/// assert_eq!(Vec::<usize>::new(), set.matches("c/foo"));
+/// ```
+pub struct GlobSet {
+    patterns: Vec<CompiledGlob>,
+}
+
+impl GlobSet {
+    /// Builds a `GlobSet` from a list of `(pattern, sep)` pairs, in the same
+    /// order they'll be reported back as indices from `matches`. Each pattern
+    /// is compiled once up front via `CompiledGlob`, so matching many
+    /// candidates against the set never re-parses a pattern.
+    pub fn new(patterns: Vec<(&str, &str)>) -> GlobSet {
+        GlobSet {
+            patterns: patterns.into_iter()
+                .map(|(pattern, sep)| CompiledGlob::new(pattern, sep))
+                .collect(),
+        }
+    }
+
+    /// Returns the indices of every pattern in this set that accepts the candidate.
+    pub fn matches(&self, candidate: &str) -> Vec<usize> {
+        self.patterns.iter()
+            .enumerate()
+            .filter(|&(_, glob)| glob.is_match(candidate))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// A fast-path boolean for when the caller only needs to know whether any
+    /// pattern in the set accepts the candidate, not which ones.
+    pub fn is_match(&self, candidate: &str) -> bool {
+        self.patterns.iter().any(|glob| glob.is_match(candidate))
+    }
+}
+
+/// One item inside a `[...]` character class: either a single byte or an
+/// inclusive `a-z`-style range.
+#[derive(Debug, PartialEq)]
+enum ClassItem {
+    Char(u8),
+    Range(u8, u8),
+}
+
+fn class_item_matches(item: &ClassItem, byte: u8) -> bool {
+    match *item {
+        ClassItem::Char(c) => c == byte,
+        ClassItem::Range(lo, hi) => byte >= lo && byte <= hi,
+    }
+}
+
+/// A single token within one segment of a compiled pattern.
+#[derive(Debug, PartialEq)]
+enum GlobToken {
+    /// A run of literal bytes that must match exactly.
+    Literal(Vec<u8>),
+    /// A `*`, matching any run of bytes within the segment.
+    Star,
+    /// A `?`, matching exactly one byte within the segment.
+    Question,
+    /// A `[...]` or `[!...]` character class, matching (or, if negated,
+    /// rejecting) exactly one byte that falls in the class.
+    Class(bool, Vec<ClassItem>),
+}
+
+/// One separator-delimited segment of a compiled pattern.
+#[derive(Debug, PartialEq)]
+enum GlobSegment {
+    /// A `**` segment, matching any number of candidate segments.
+    DoubleStar,
+    /// An ordinary segment, pre-tokenized into literal, `*`, `?`, and `[...]` pieces.
+    Single(Vec<GlobToken>),
+}
+
+fn tokenize_segment(segment: &str) -> Vec<GlobToken> {
+    let bytes = segment.as_bytes();
+    let mut tokens = Vec::new();
+    let mut literal = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        if byte == b'\\' && i + 1 < bytes.len() {
+            literal.push(bytes[i + 1]);
+            i += 2;
+            continue;
+        }
+        if byte == b'*' {
+            flush_literal(&mut tokens, &mut literal);
+            tokens.push(GlobToken::Star);
+            i += 1;
+            continue;
+        }
+        if byte == b'?' {
+            flush_literal(&mut tokens, &mut literal);
+            tokens.push(GlobToken::Question);
+            i += 1;
+            continue;
+        }
+        if byte == b'[' {
+            if let Some((token, consumed)) = parse_class(&bytes[i..]) {
+                flush_literal(&mut tokens, &mut literal);
+                tokens.push(token);
+                i += consumed;
+                continue;
+            }
+        }
+        literal.push(byte);
+        i += 1;
+    }
+    flush_literal(&mut tokens, &mut literal);
+    tokens
+}
+
+fn flush_literal(tokens: &mut Vec<GlobToken>, literal: &mut Vec<u8>) {
+    if !literal.is_empty() {
+        tokens.push(GlobToken::Literal(literal.clone()));
+        literal.clear();
+    }
+}
+
+// Parses a "[...]" or "[!...]" class starting at `bytes[0] == b'['`. Returns
+// the parsed token and how many bytes it consumed, or `None` if there's no
+// unescaped closing "]" (in which case the "[" is treated as a literal).
+fn parse_class(bytes: &[u8]) -> Option<(GlobToken, usize)> {
+    let mut i = 1;
+    let negated = if bytes.get(i) == Some(&b'!') {
+        i += 1;
+        true
     } else {
-        // If there are no wildcards, we can do a straight comparison on the segments
-        pattern[0] == candidate[0] && matches_segments(&pattern[1..], &candidate[1..])
+        false
+    };
+    let mut items = Vec::new();
+    while i < bytes.len() && bytes[i] != b']' {
+        if bytes[i] == b'\\' && i + 1 < bytes.len() {
+            items.push(ClassItem::Char(bytes[i + 1]));
+            i += 2;
+        } else if i + 2 < bytes.len() && bytes[i + 1] == b'-' && bytes[i + 2] != b']' {
+            items.push(ClassItem::Range(bytes[i], bytes[i + 2]));
+            i += 3;
+        } else {
+            items.push(ClassItem::Char(bytes[i]));
+            i += 1;
+        }
+    }
+    if i >= bytes.len() {
+        None
+    } else {
+        Some((GlobToken::Class(negated, items), i + 1))
     }
 }
 
-fn matches_glob(pattern: &str, candidate: &str) -> bool {
-    if pattern.len() == 0 {
-        candidate.len() == 0
-    } else if candidate.len() == 0 {
-        pattern == "*"
-    } else if pattern.chars().nth(0).unwrap() == '*' {
-        // Use it or lose it. We either consume a candidate charactor or stop consuming
-        matches_glob(&pattern[1..], candidate) || matches_glob(pattern, &candidate[1..])
+fn literal_at(candidate: &[u8], c: usize, lit: &[u8]) -> bool {
+    let n = lit.len();
+    c + n <= candidate.len() && &candidate[c..c + n] == lit
+}
+
+// Returns how many bytes the UTF-8 character starting at `candidate[c]`
+// occupies, clamped to what's actually left in `candidate`. Falls back to 1
+// for a stray continuation byte, which can't start an encoded char but still
+// has to consume something.
+fn char_len_at(candidate: &[u8], c: usize) -> usize {
+    let byte = candidate[c];
+    let len = if byte & 0x80 == 0 {
+        1
+    } else if byte & 0xE0 == 0xC0 {
+        2
+    } else if byte & 0xF0 == 0xE0 {
+        3
+    } else if byte & 0xF8 == 0xF0 {
+        4
+    } else {
+        1
+    };
+    len.min(candidate.len() - c)
+}
+
+// `?` and `[...]`/`[!...]` match exactly one *character*, not one byte, so a
+// multi-byte UTF-8 char has to be consumed whole or it'll desync every token
+// after it. A class's items are themselves plain bytes (ASCII ranges like
+// `[a-z0-9]`), so a multi-byte char can only ever satisfy a *negated* class.
+fn class_match_len(candidate: &[u8], c: usize, negated: bool, items: &[ClassItem]) -> Option<usize> {
+    let byte = candidate[c];
+    if byte < 0x80 {
+        if items.iter().any(|item| class_item_matches(item, byte)) != negated {
+            Some(1)
+        } else {
+            None
+        }
+    } else if negated {
+        Some(char_len_at(candidate, c))
+    } else {
+        None
+    }
+}
+
+// Linear greedy two-pointer scan over pre-tokenized literal, `*`, `?` and
+// `[...]` pieces; `matches_glob` below tokenizes its pattern and calls this too.
+// `star_t` remembers the last `*` token we passed and `star_c` the candidate
+// position it had absorbed up to; on a mismatch we let it absorb one more byte
+// instead of recursing, which keeps this O(n*m) even on adversarial input.
+fn tokens_match(tokens: &[GlobToken], candidate: &[u8]) -> bool {
+    let mut t = 0;
+    let mut c = 0;
+    let mut star_t: Option<usize> = None;
+    let mut star_c = 0;
+    while c < candidate.len() {
+        if tokens.get(t) == Some(&GlobToken::Star) {
+            star_t = Some(t);
+            star_c = c;
+            t += 1;
+            continue;
+        }
+        let advance = match tokens.get(t) {
+            Some(GlobToken::Literal(lit)) if literal_at(candidate, c, lit) => Some(lit.len()),
+            Some(&GlobToken::Question) => Some(char_len_at(candidate, c)),
+            Some(&GlobToken::Class(negated, ref items)) => class_match_len(candidate, c, negated, items),
+            _ => None,
+        };
+        match advance {
+            Some(len) => {
+                c += len;
+                t += 1;
+            }
+            None => match star_t {
+                Some(st) => {
+                    t = st + 1;
+                    star_c += 1;
+                    c = star_c;
+                }
+                None => return false,
+            },
+        }
+    }
+    while tokens.get(t) == Some(&GlobToken::Star) {
+        t += 1;
+    }
+    t == tokens.len()
+}
+
+// If a segment is not "**" and has no "*", "?" or "[...]" token in it at all,
+// it can be compared with a plain byte equality check instead of going through
+// `tokens_match`.
+fn plain_literal_segment(segment: &GlobSegment) -> Option<Vec<u8>> {
+    match segment {
+        GlobSegment::Single(tokens) if tokens.is_empty() => Some(Vec::new()),
+        GlobSegment::Single(tokens) if tokens.len() == 1 => match tokens[0] {
+            GlobToken::Literal(ref bytes) => Some(bytes.clone()),
+            GlobToken::Star | GlobToken::Question | GlobToken::Class(..) => None,
+        },
+        _ => None,
+    }
+}
+
+// A required-extension segment is exactly `*<literal>`, e.g. the `*.jpg` in
+// `a/**/*.jpg`: whatever the candidate's last segment is, it must end with
+// `<literal>`.
+fn required_extension(segments: &[GlobSegment]) -> Option<Vec<u8>> {
+    match segments.last() {
+        Some(GlobSegment::Single(tokens)) if tokens.len() == 2 => match (&tokens[0], &tokens[1]) {
+            (GlobToken::Star, GlobToken::Literal(lit)) => Some(lit.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn literal_prefix(segments: &[GlobSegment]) -> Vec<Vec<u8>> {
+    segments.iter()
+        .map(plain_literal_segment)
+        .take_while(Option::is_some)
+        .map(Option::unwrap)
+        .collect()
+}
+
+fn literal_suffix(segments: &[GlobSegment]) -> Vec<Vec<u8>> {
+    let mut suffix: Vec<Vec<u8>> = segments.iter().rev()
+        .map(plain_literal_segment)
+        .take_while(Option::is_some)
+        .map(Option::unwrap)
+        .collect();
+    suffix.reverse();
+    suffix
+}
+
+/// A cheap pre-filter computed once from a pattern's segments, mirroring the
+/// internal matching-strategy classifiers that globset uses. Most patterns in
+/// a large policy document can be rejected (or, for a pure literal, fully
+/// decided) without ever running the general segment matcher.
+enum MatchStrategy {
+    /// No wildcards anywhere: decide the match with a plain byte comparison
+    /// per segment.
+    Literal(Vec<Vec<u8>>),
+    /// The pattern ends in `*<ext>` (optionally preceded by `**`): reject
+    /// candidates whose last segment doesn't end in `<ext>` before running
+    /// the general matcher.
+    Extension(Vec<u8>),
+    /// The pattern starts with one or more literal segments before its first
+    /// wildcard: reject candidates that don't start with them.
+    Prefix(Vec<Vec<u8>>),
+    /// The pattern ends with one or more literal segments after its last
+    /// wildcard: reject candidates that don't end with them.
+    Suffix(Vec<Vec<u8>>),
+    /// No cheap pre-filter applies; go straight to the general matcher.
+    General,
+}
+
+fn classify(segments: &[GlobSegment]) -> MatchStrategy {
+    let literals: Vec<Vec<u8>> = segments.iter().filter_map(plain_literal_segment).collect();
+    if literals.len() == segments.len() {
+        return MatchStrategy::Literal(literals);
+    }
+    if let Some(ext) = required_extension(segments) {
+        return MatchStrategy::Extension(ext);
+    }
+    let prefix = literal_prefix(segments);
+    if !prefix.is_empty() {
+        return MatchStrategy::Prefix(prefix);
+    }
+    let suffix = literal_suffix(segments);
+    if !suffix.is_empty() {
+        return MatchStrategy::Suffix(suffix);
+    }
+    MatchStrategy::General
+}
+
+// One brace-expanded alternative of a compiled pattern, with its own segments
+// and its own cheap matching strategy.
+struct CompiledAlternative {
+    segments: Vec<GlobSegment>,
+    strategy: MatchStrategy,
+}
+
+fn compile_alternative(pattern: &str, sep: &str) -> CompiledAlternative {
+    let raw_segments: Vec<&str> = match sep {
+        "" => vec!(pattern),
+        _ => pattern.split(sep).collect(),
+    };
+    let segments: Vec<GlobSegment> = raw_segments.into_iter()
+        .map(|segment| if segment == "**" {
+            GlobSegment::DoubleStar
+        } else {
+            GlobSegment::Single(tokenize_segment(segment))
+        })
+        .collect();
+    let strategy = classify(&segments[..]);
+    CompiledAlternative {
+        segments,
+        strategy,
+    }
+}
+
+fn alternative_is_match(alternative: &CompiledAlternative, candidate_segments: &[&str]) -> bool {
+    match alternative.strategy {
+        MatchStrategy::Literal(ref literals) =>
+            return literals.len() == candidate_segments.len() &&
+                literals.iter().zip(candidate_segments.iter())
+                    .all(|(lit, seg)| &lit[..] == seg.as_bytes()),
+        MatchStrategy::Extension(ref ext) => {
+            if !candidate_segments.last().is_some_and(|last| last.as_bytes().ends_with(&ext[..])) {
+                return false;
+            }
+        }
+        MatchStrategy::Prefix(ref prefix) => {
+            if candidate_segments.len() < prefix.len() ||
+                !prefix.iter().zip(candidate_segments.iter()).all(|(lit, seg)| &lit[..] == seg.as_bytes()) {
+                return false;
+            }
+        }
+        MatchStrategy::Suffix(ref suffix) => {
+            if candidate_segments.len() < suffix.len() ||
+                !suffix.iter().rev().zip(candidate_segments.iter().rev())
+                    .all(|(lit, seg)| &lit[..] == seg.as_bytes()) {
+                return false;
+            }
+        }
+        MatchStrategy::General => {}
+    }
+    compiled_matches_segments(&alternative.segments[..], candidate_segments)
+}
+
+/// A pattern that has been parsed once into a reusable structure, so that
+/// matching many candidates against it (the common case when authorizing
+/// many requests against one cached policy) avoids re-splitting the pattern
+/// string and re-walking it character by character on every call. At build
+/// time the pattern is also expanded into its brace alternatives (if any)
+/// and each is classified into a cheap matching strategy, so that most
+/// candidates in a large policy document can be rejected (or, for a pure
+/// literal pattern, fully decided) before ever running the general segment
+/// matcher.
+/// # Examples
+/// ```
+/// use hapolicy::glob::CompiledGlob;
+/// let glob = CompiledGlob::new("a/**/*.jpg", "/");
+/// assert_eq!(true, glob.is_match("a/foo/bar/baz.jpg"));
+/// assert_eq!(false, glob.is_match("a/foo/bar/baz"));
+/// ```
+pub struct CompiledGlob {
+    sep: String,
+    alternatives: Vec<CompiledAlternative>,
+}
+
+impl CompiledGlob {
+    /// Parses `pattern` once into a reusable `CompiledGlob`. `sep` has the same
+    /// meaning as in `matches`: it splits both the pattern and any later
+    /// candidates into segments, and an empty separator disables splitting.
+    pub fn new(pattern: &str, sep: &str) -> CompiledGlob {
+        CompiledGlob {
+            sep: sep.to_string(),
+            alternatives: expand_braces(pattern).iter()
+                .map(|alternative| compile_alternative(alternative, sep))
+                .collect(),
+        }
+    }
+
+    /// Returns true iff this pattern accepts the candidate.
+    pub fn is_match(&self, candidate: &str) -> bool {
+        let candidate_segments: Vec<&str> = match &self.sep[..] {
+            "" => vec!(candidate),
+            sep => candidate.split(sep).collect(),
+        };
+        self.alternatives.iter().any(|alternative| alternative_is_match(alternative, &candidate_segments[..]))
+    }
+}
+
+// Same linear two-pointer strategy as `matches_segments`, at the segment level:
+// a `DoubleStar` segment is recorded as the backtrack point and absorbs one
+// more candidate segment each time a later segment fails to match.
+fn compiled_matches_segments(pattern: &[GlobSegment], candidate: &[&str]) -> bool {
+    let mut p = 0;
+    let mut c = 0;
+    let mut star_p: Option<usize> = None;
+    let mut star_c = 0;
+    while c < candidate.len() {
+        match pattern.get(p) {
+            Some(&GlobSegment::DoubleStar) => {
+                star_p = Some(p);
+                star_c = c;
+                p += 1;
+            }
+            Some(GlobSegment::Single(tokens)) if tokens_match(tokens, candidate[c].as_bytes()) => {
+                p += 1;
+                c += 1;
+            }
+            _ => {
+                match star_p {
+                    Some(sp) => {
+                        p = sp + 1;
+                        star_c += 1;
+                        c = star_c;
+                    }
+                    None => return false,
+                }
+            }
+        }
+    }
+    while pattern.get(p) == Some(&GlobSegment::DoubleStar) {
+        p += 1;
+    }
+    p == pattern.len()
+}
+
+fn segment_matches(pattern: &str, candidate: &str) -> bool {
+    if pattern.bytes().any(|b| b == b'*' || b == b'?' || b == b'[' || b == b'\\') {
+        matches_glob(pattern, candidate)
     } else {
-        pattern.chars().nth(0).unwrap() == candidate.chars().nth(0).unwrap() &&
-        matches_glob(&pattern[1..], &candidate[1..])
+        pattern == candidate
+    }
+}
+
+// Linear greedy two-pointer scan at the segment level: `star_p` remembers the
+// last "**" segment we passed and `star_c` the candidate position it had
+// absorbed up to. On a mismatch we let it absorb one more candidate segment
+// and retry, instead of recursing into both "use it" and "lose it" branches.
+fn matches_segments(pattern: &[&str], candidate: &[&str]) -> bool {
+    let mut p = 0;
+    let mut c = 0;
+    let mut star_p: Option<usize> = None;
+    let mut star_c = 0;
+    while c < candidate.len() {
+        if pattern.get(p) == Some(&"**") {
+            star_p = Some(p);
+            star_c = c;
+            p += 1;
+        } else if p < pattern.len() && segment_matches(pattern[p], candidate[c]) {
+            p += 1;
+            c += 1;
+        } else {
+            match star_p {
+                Some(sp) => {
+                    p = sp + 1;
+                    star_c += 1;
+                    c = star_c;
+                }
+                None => return false,
+            }
+        }
+    }
+    while pattern.get(p) == Some(&"**") {
+        p += 1;
     }
+    p == pattern.len()
+}
+
+// Tokenizes the pattern and reuses the same linear two-pointer engine that
+// backs `CompiledGlob`, so "*", "**", "?", "[...]" and escaping behave
+// identically whether or not the pattern has been pre-compiled.
+fn matches_glob(pattern: &str, candidate: &str) -> bool {
+    tokens_match(&tokenize_segment(pattern), candidate.as_bytes())
 }
 
 #[cfg(test)]
@@ -86,6 +693,8 @@ mod tests {
     use super::matches_glob;
     use super::matches_segments;
     use super::matches;
+    use super::GlobSet;
+    use super::CompiledGlob;
 
     #[test]
     fn matches_use_cases() {
@@ -262,4 +871,183 @@ mod tests {
         assert_eq!(false, matches_segments(&["a", "**", "b"], &["a", "bar", "blah", "foo"]));
         assert_eq!(true, matches_segments(&["a", "**", "b"], &["a", "bar", "blah", "foo", "b"]));
     }
+
+    #[test]
+    fn globset_reports_indices_of_all_matching_patterns() {
+        let set = GlobSet::new(vec![
+            ("a/*", "/"),
+            ("b/*", "/"),
+            ("a/**", "/"),
+        ]);
+        assert_eq!(vec![0, 2], set.matches("a/foo"));
+        assert_eq!(vec![1], set.matches("b/foo"));
+        assert_eq!(Vec::<usize>::new(), set.matches("c/foo"));
+    }
+
+    #[test]
+    fn globset_is_match_fast_path() {
+        let set = GlobSet::new(vec![
+            ("a/*", "/"),
+            ("b/*", "/"),
+        ]);
+        assert_eq!(true, set.is_match("a/foo"));
+        assert_eq!(true, set.is_match("b/foo"));
+        assert_eq!(false, set.is_match("c/foo"));
+    }
+
+    #[test]
+    fn globset_empty_set_matches_nothing() {
+        let set = GlobSet::new(vec![]);
+        assert_eq!(false, set.is_match("a"));
+        assert_eq!(Vec::<usize>::new(), set.matches("a"));
+    }
+
+    #[test]
+    fn compiled_glob_matches_simple_patterns() {
+        let glob = CompiledGlob::new("a/b", "/");
+        assert_eq!(true, glob.is_match("a/b"));
+        assert_eq!(false, glob.is_match("b/a"));
+    }
+
+    #[test]
+    fn compiled_glob_matches_single_wildcards() {
+        let glob = CompiledGlob::new("a/*/c", "/");
+        assert_eq!(true, glob.is_match("a/b/c"));
+        assert_eq!(true, glob.is_match("a/booooo/c"));
+        assert_eq!(false, glob.is_match("a/b/c/d"));
+    }
+
+    #[test]
+    fn compiled_glob_matches_dual_wildcards() {
+        let glob = CompiledGlob::new("a/**/*.jpg", "/");
+        assert_eq!(true, glob.is_match("a/foo/bar/baz.jpg"));
+        assert_eq!(false, glob.is_match("a/foo/bar/baz"));
+    }
+
+    #[test]
+    fn matches_glob_handles_adversarial_patterns_without_blowing_up() {
+        // "*a*a*a*...*a" against a long string with no trailing "a" used to be
+        // exponential under the recursive "use it or lose it" backtracking.
+        let pattern: String = "*a".repeat(20) + "*";
+        let candidate = "b".repeat(200);
+        assert_eq!(false, matches_glob(&pattern, &candidate));
+    }
+
+    #[test]
+    fn compiled_glob_reusable_across_many_candidates() {
+        let glob = CompiledGlob::new("ht:myapp:*", ":");
+        assert_eq!(true, glob.is_match("ht:myapp:foo"));
+        assert_eq!(true, glob.is_match("ht:myapp:bar"));
+        assert_eq!(false, glob.is_match("ht:otherapp:bar"));
+    }
+
+    #[test]
+    fn compiled_glob_literal_strategy_matches_exactly() {
+        let glob = CompiledGlob::new("a/b/c", "/");
+        assert_eq!(true, glob.is_match("a/b/c"));
+        assert_eq!(false, glob.is_match("a/b/d"));
+        assert_eq!(false, glob.is_match("a/b"));
+        assert_eq!(false, glob.is_match("a/b/c/d"));
+    }
+
+    #[test]
+    fn compiled_glob_extension_strategy_rejects_wrong_extension_cheaply() {
+        let glob = CompiledGlob::new("a/**/*.jpg", "/");
+        assert_eq!(false, glob.is_match("a/foo/bar/baz.png"));
+        assert_eq!(true, glob.is_match("a/foo/bar/baz.jpg"));
+    }
+
+    #[test]
+    fn compiled_glob_prefix_strategy_rejects_wrong_prefix_cheaply() {
+        let glob = CompiledGlob::new("ht:myapp:myservice:hierarchical/path/*", "/");
+        assert_eq!(false, glob.is_match("ht:otherapp:myservice:hierarchical/path/foo"));
+        assert_eq!(true, glob.is_match("ht:myapp:myservice:hierarchical/path/foo"));
+    }
+
+    #[test]
+    fn compiled_glob_suffix_strategy_rejects_wrong_suffix_cheaply() {
+        let glob = CompiledGlob::new("*/read", "/");
+        assert_eq!(false, glob.is_match("foo/write"));
+        assert_eq!(true, glob.is_match("foo/read"));
+    }
+
+    #[test]
+    fn matches_works_with_question_mark() {
+        assert_eq!(true, matches("a?c", "abc", ""));
+        assert_eq!(true, matches("a?c", "axc", ""));
+        assert_eq!(false, matches("a?c", "ac", ""));
+        assert_eq!(false, matches("a?c", "abbc", ""));
+        assert_eq!(false, matches("?", "", ""));
+    }
+
+    #[test]
+    fn matches_works_with_character_classes() {
+        assert_eq!(true, matches("[abc]", "a", ""));
+        assert_eq!(true, matches("[abc]", "b", ""));
+        assert_eq!(false, matches("[abc]", "d", ""));
+
+        assert_eq!(true, matches("[a-z0-9]", "q", ""));
+        assert_eq!(true, matches("[a-z0-9]", "5", ""));
+        assert_eq!(false, matches("[a-z0-9]", "Q", ""));
+
+        assert_eq!(true, matches("[!a-z]", "A", ""));
+        assert_eq!(false, matches("[!a-z]", "a", ""));
+    }
+
+    #[test]
+    fn matches_works_with_brace_alternation() {
+        assert_eq!(true, matches("{read,write}", "read", ""));
+        assert_eq!(true, matches("{read,write}", "write", ""));
+        assert_eq!(false, matches("{read,write}", "delete", ""));
+
+        assert_eq!(true, matches("user-[0-9]*/{read,write}", "user-42/read", "/"));
+        assert_eq!(true, matches("user-[0-9]*/{read,write}", "user-42/write", "/"));
+        assert_eq!(false, matches("user-[0-9]*/{read,write}", "user-42/delete", "/"));
+        assert_eq!(false, matches("user-[0-9]*/{read,write}", "user-abc/read", "/"));
+    }
+
+    #[test]
+    fn matches_honors_backslash_escapes() {
+        assert_eq!(true, matches("a\\*b", "a*b", ""));
+        assert_eq!(false, matches("a\\*b", "axb", ""));
+        assert_eq!(true, matches("a\\?b", "a?b", ""));
+        assert_eq!(true, matches("a\\[b", "a[b", ""));
+        assert_eq!(true, matches("a\\{b", "a{b", ""));
+        assert_eq!(true, matches("{a\\,b,c}", "a,b", ""));
+    }
+
+    #[test]
+    fn compiled_glob_matches_character_classes_and_braces() {
+        let glob = CompiledGlob::new("user-[0-9]*/{read,write}", "/");
+        assert_eq!(true, glob.is_match("user-42/read"));
+        assert_eq!(true, glob.is_match("user-7/write"));
+        assert_eq!(false, glob.is_match("user-42/delete"));
+        assert_eq!(false, glob.is_match("user-abc/read"));
+    }
+
+    #[test]
+    fn matches_does_not_mistake_class_contents_for_brace_syntax() {
+        assert_eq!(true, matches("[{,}]", "{", ""));
+        assert_eq!(true, matches("[{,}]", ",", ""));
+        assert_eq!(true, matches("[{,}]", "}", ""));
+        assert_eq!(false, matches("[{,}]", "a", ""));
+    }
+
+    #[test]
+    fn matches_caps_exponential_brace_expansion() {
+        // Each repeated "{aa,bb}" group doubles the cross product; left
+        // unbounded this pattern would take minutes to expand. It should
+        // instead fall back to being treated as brace-free (and therefore
+        // not match, since the literal braces aren't in the candidate).
+        let pattern: String = "{aa,bb}".repeat(20);
+        assert_eq!(false, matches(&pattern, &"aa".repeat(20), ""));
+    }
+
+    #[test]
+    fn matches_works_with_multi_byte_characters() {
+        assert_eq!(true, matches("a?c", "a\u{e9}c", ""));
+        assert_eq!(true, matches("a[!a-z]c", "a\u{e9}c", ""));
+        assert_eq!(false, matches("a[a-z]c", "a\u{e9}c", ""));
+        assert_eq!(true, matches("?", "\u{1f600}", ""));
+    }
 }
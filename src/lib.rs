@@ -43,7 +43,13 @@
 // 	Hierarchy   []string
 // }
 
-mod glob;
+// This crate's tests spell out `assert_eq!(true, ...)`/`assert_eq!(false, ...)`
+// throughout so the expected value always reads first; that's the established
+// convention here, not an oversight.
+#![allow(clippy::bool_assert_comparison)]
+
+pub mod glob;
+pub mod policy;
 
 #[test]
 fn it_works() {
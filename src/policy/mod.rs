@@ -0,0 +1,303 @@
+use std::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::glob::{CompiledGlob, GlobSet};
+
+/// Whether a `Statement` grants or revokes access. An explicit `Deny` always
+/// overrides any number of matching `Allow`s, and a token with no matching
+/// statement at all is denied by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Effect {
+    Allow,
+    Deny,
+}
+
+/// A single grant or revocation within a token. `effect` applies to a given
+/// action/resource pair whenever that action matches at least one entry in
+/// `actions` and that resource matches at least one entry in `resources`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Statement {
+    pub effect: Effect,
+    pub actions: Vec<String>,
+    pub resources: Vec<String>,
+}
+
+/// The contents of an `HToken` before it has been signed: the policy
+/// `version`, the `statements` to evaluate, and a Unix `expiration` timestamp
+/// (in seconds) at and after which the token is no longer valid. An `expiration` of
+/// `0` means the token never expires.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct UnsignedHToken {
+    pub version: String,
+    pub statements: Vec<Statement>,
+    #[serde(default)]
+    pub expiration: u64,
+}
+
+/// A signed hierarchical authorization token: an `UnsignedHToken` plus the
+/// `signature` that vouches for it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HToken {
+    #[serde(flatten)]
+    pub token: UnsignedHToken,
+    pub signature: String,
+}
+
+/// The outcome of evaluating a token against an action and a resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    Deny,
+}
+
+// A resource is a `:`-separated service path followed by a `/`-separated
+// hierarchy in its final segment, e.g. "ht:myapp:myservice:a/b/*" has service
+// path "ht:myapp:myservice" and hierarchy "a/b/*". The two halves are compiled
+// and matched independently, each with its own separator.
+struct CompiledResource {
+    service_path: CompiledGlob,
+    hierarchy: CompiledGlob,
+}
+
+impl CompiledResource {
+    fn compile(pattern: &str) -> CompiledResource {
+        let (service_path, hierarchy) = split_resource(pattern);
+        CompiledResource {
+            service_path: CompiledGlob::new(service_path, ":"),
+            hierarchy: CompiledGlob::new(hierarchy, "/"),
+        }
+    }
+
+    fn is_match(&self, candidate: &str) -> bool {
+        let (service_path, hierarchy) = split_resource(candidate);
+        self.service_path.is_match(service_path) && self.hierarchy.is_match(hierarchy)
+    }
+}
+
+fn split_resource(resource: &str) -> (&str, &str) {
+    match resource.rfind(':') {
+        Some(i) => (&resource[..i], &resource[i + 1..]),
+        None => ("", resource),
+    }
+}
+
+// One `Statement`, pre-compiled so that evaluating many action/resource pairs
+// against the same token never re-tokenizes or re-classifies its patterns.
+struct CompiledStatement {
+    effect: Effect,
+    actions: GlobSet,
+    resources: Vec<CompiledResource>,
+}
+
+impl CompiledStatement {
+    fn compile(statement: &Statement) -> CompiledStatement {
+        CompiledStatement {
+            effect: statement.effect,
+            actions: GlobSet::new(statement.actions.iter().map(|action| (action.as_str(), ":")).collect()),
+            resources: statement.resources.iter().map(|resource| CompiledResource::compile(resource)).collect(),
+        }
+    }
+
+    fn matches(&self, action: &str, resource: &str) -> bool {
+        self.actions.is_match(action) && self.resources.iter().any(|r| r.is_match(resource))
+    }
+}
+
+/// An `HToken` whose statements have been compiled once via `GlobSet` and
+/// `CompiledGlob`, so that `evaluate` can check an action/resource pair
+/// against every statement in one pass without re-parsing any pattern. This
+/// is the form `evaluate` expects, since the common case is authorizing many
+/// requests against one cached policy.
+/// # Examples
+/// ```
+/// use hapolicy::policy::{CompiledHToken, Decision, Effect, HToken, Statement, UnsignedHToken, evaluate};
+///
+/// let token = HToken {
+///     token: UnsignedHToken {
+///         version: "2015-10-7".to_string(),
+///         statements: vec![Statement {
+///             effect: Effect::Allow,
+///             actions: vec!["myservice:MyAction1".to_string()],
+///             resources: vec!["ht:myapp:myservice:hierarchical/path/*".to_string()],
+///         }],
+///         expiration: 0,
+///     },
+///     signature: "".to_string(),
+/// };
+/// let compiled = CompiledHToken::compile(&token);
+///
+/// assert_eq!(Decision::Allow, evaluate(&compiled, "myservice:MyAction1", "ht:myapp:myservice:hierarchical/path/foo", 0));
+/// assert_eq!(Decision::Deny, evaluate(&compiled, "myservice:MyAction2", "ht:myapp:myservice:hierarchical/path/foo", 0));
+/// ```
+pub struct CompiledHToken {
+    expiration: u64,
+    statements: Vec<CompiledStatement>,
+}
+
+impl CompiledHToken {
+    /// Compiles every statement in `token` once, up front.
+    pub fn compile(token: &HToken) -> CompiledHToken {
+        CompiledHToken {
+            expiration: token.token.expiration,
+            statements: token.token.statements.iter().map(CompiledStatement::compile).collect(),
+        }
+    }
+}
+
+/// Evaluates `token` against `action` and `resource`, as of `now` (a Unix
+/// timestamp in seconds). Applies explicit-deny-overrides-allow semantics: a
+/// single matching `Deny` statement denies the request outright regardless of
+/// any matching `Allow`s. A token with no matching statement at all, or one
+/// evaluated at or after its `expiration`, is denied by default.
+pub fn evaluate(token: &CompiledHToken, action: &str, resource: &str, now: u64) -> Decision {
+    if token.expiration != 0 && now >= token.expiration {
+        return Decision::Deny;
+    }
+    let mut allowed = false;
+    for statement in &token.statements {
+        if !statement.matches(action, resource) {
+            continue;
+        }
+        match statement.effect {
+            Effect::Deny => return Decision::Deny,
+            Effect::Allow => allowed = true,
+        }
+    }
+    if allowed {
+        Decision::Allow
+    } else {
+        Decision::Deny
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(statements: Vec<Statement>) -> HToken {
+        HToken {
+            token: UnsignedHToken {
+                version: "2015-10-7".to_string(),
+                statements,
+                expiration: 0,
+            },
+            signature: "".to_string(),
+        }
+    }
+
+    fn allow(actions: Vec<&str>, resources: Vec<&str>) -> Statement {
+        Statement {
+            effect: Effect::Allow,
+            actions: actions.iter().map(|s| s.to_string()).collect(),
+            resources: resources.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn deny(actions: Vec<&str>, resources: Vec<&str>) -> Statement {
+        Statement {
+            effect: Effect::Deny,
+            actions: actions.iter().map(|s| s.to_string()).collect(),
+            resources: resources.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn evaluate_allows_a_matching_action_and_resource() {
+        let t = CompiledHToken::compile(&token(vec![allow(
+            vec!["myservice:MyAction1"],
+            vec!["ht:myapp:myservice:hierarchical/path/*"],
+        )]));
+        assert_eq!(Decision::Allow, evaluate(&t, "myservice:MyAction1", "ht:myapp:myservice:hierarchical/path/foo", 0));
+    }
+
+    #[test]
+    fn evaluate_denies_by_default_when_nothing_matches() {
+        let t = CompiledHToken::compile(&token(vec![allow(
+            vec!["myservice:MyAction1"],
+            vec!["ht:myapp:myservice:hierarchical/path/*"],
+        )]));
+        assert_eq!(Decision::Deny, evaluate(&t, "myservice:MyAction2", "ht:myapp:myservice:hierarchical/path/foo", 0));
+        assert_eq!(Decision::Deny, evaluate(&t, "myservice:MyAction1", "ht:myapp:myservice:other/path/foo", 0));
+    }
+
+    #[test]
+    fn evaluate_lets_an_explicit_deny_override_an_allow() {
+        let t = CompiledHToken::compile(&token(vec![
+            allow(vec!["myservice:*"], vec!["ht:myapp:myservice:**"]),
+            deny(vec!["myservice:MyAction1"], vec!["ht:myapp:myservice:hierarchical/path/*"]),
+        ]));
+        assert_eq!(Decision::Deny, evaluate(&t, "myservice:MyAction1", "ht:myapp:myservice:hierarchical/path/foo", 0));
+        assert_eq!(Decision::Allow, evaluate(&t, "myservice:MyAction2", "ht:myapp:myservice:hierarchical/path/foo", 0));
+    }
+
+    #[test]
+    fn evaluate_denies_an_expired_token() {
+        let t = CompiledHToken::compile(&HToken {
+            token: UnsignedHToken {
+                version: "2015-10-7".to_string(),
+                statements: vec![allow(vec!["myservice:*"], vec!["ht:myapp:myservice:**"])],
+                expiration: 100,
+            },
+            signature: "".to_string(),
+        });
+        assert_eq!(Decision::Allow, evaluate(&t, "myservice:MyAction1", "ht:myapp:myservice:foo", 99));
+        assert_eq!(Decision::Deny, evaluate(&t, "myservice:MyAction1", "ht:myapp:myservice:foo", 100));
+    }
+
+    #[test]
+    fn evaluate_treats_zero_expiration_as_never_expiring() {
+        let t = CompiledHToken::compile(&token(vec![allow(vec!["myservice:*"], vec!["ht:myapp:myservice:**"])]));
+        assert_eq!(Decision::Allow, evaluate(&t, "myservice:MyAction1", "ht:myapp:myservice:foo", u64::MAX));
+    }
+
+    #[test]
+    fn evaluate_reuses_one_compiled_token_across_many_requests() {
+        let t = CompiledHToken::compile(&token(vec![allow(
+            vec!["myservice:Read", "myservice:Write"],
+            vec!["ht:myapp:myservice:a/*", "ht:myapp:myservice:b/*"],
+        )]));
+        assert_eq!(Decision::Allow, evaluate(&t, "myservice:Read", "ht:myapp:myservice:a/1", 0));
+        assert_eq!(Decision::Allow, evaluate(&t, "myservice:Write", "ht:myapp:myservice:b/2", 0));
+        assert_eq!(Decision::Deny, evaluate(&t, "myservice:Delete", "ht:myapp:myservice:a/1", 0));
+        assert_eq!(Decision::Deny, evaluate(&t, "myservice:Read", "ht:myapp:myservice:c/1", 0));
+    }
+
+    #[test]
+    fn compiled_resource_splits_on_colon_then_slash() {
+        let resource = CompiledResource::compile("ht:myapp:myservice:a/*/c");
+        assert_eq!(true, resource.is_match("ht:myapp:myservice:a/b/c"));
+        assert_eq!(false, resource.is_match("ht:otherapp:myservice:a/b/c"));
+        assert_eq!(false, resource.is_match("ht:myapp:myservice:a/b/c/d"));
+    }
+
+    #[test]
+    fn statement_serializes_to_the_documented_json_shape() {
+        let s = allow(vec!["myservice:MyAction1", "myservice:MyAction2"], vec!["ht:myapp:myservice:hierarchical/path/*"]);
+        let json = serde_json::to_string(&s).unwrap();
+        let round_tripped: Statement = serde_json::from_str(&json).unwrap();
+        assert_eq!(s, round_tripped);
+        assert_eq!(true, json.contains("\"Effect\":\"Allow\""));
+        assert_eq!(true, json.contains("\"Actions\":["));
+        assert_eq!(true, json.contains("\"Resources\":["));
+    }
+
+    #[test]
+    fn unsigned_htoken_deserializes_the_documented_example_without_an_expiration() {
+        let json = r#"{
+            "Version": "2015-10-7",
+            "Statements": [
+                {
+                    "Effect": "Allow",
+                    "Actions": ["myservice:MyAction1", "myservice:MyAction2"],
+                    "Resources": ["ht:myapp:myservice:hierarchical/path/*"]
+                }
+            ]
+        }"#;
+        let token: UnsignedHToken = serde_json::from_str(json).unwrap();
+        assert_eq!(0, token.expiration);
+    }
+}